@@ -1,4 +1,5 @@
 pub mod lexer {
+    use std::collections::VecDeque;
     use std::error::Error;
     use std::fmt::{self, Display, Formatter};
     use std::iter::Peekable;
@@ -8,7 +9,7 @@ pub mod lexer {
     #[derive(Debug, Clone, Copy)]
     pub struct SymbolError;
 
-    #[derive(Debug)]
+    #[derive(Debug, Clone)]
     pub enum SpecialSymbol {
         // `<` and `>`
         LeftAngleBracket,
@@ -42,6 +43,24 @@ pub mod lexer {
         Comma,
         // ;
         Semicolon,
+        // :
+        Colon,
+
+        // arithmetic and bitwise operators, and their `OP=` compound forms
+        Plus,
+        Minus,
+        Multiply,
+        Divide,
+        Ampersand,
+        Pipe,
+        Caret,
+        PlusAssign,
+        MinusAssign,
+        MultiplyAssign,
+        DivideAssign,
+        AndAssign,
+        OrAssign,
+        XorAssign,
     }
 
     impl FromStr for SpecialSymbol {
@@ -64,6 +83,22 @@ pub mod lexer {
                 "<=" => Ok(SpecialSymbol::SmallerOrEqual),
                 "," => Ok(SpecialSymbol::Comma),
                 ";" => Ok(SpecialSymbol::Semicolon),
+                ":" => Ok(SpecialSymbol::Colon),
+
+                "+" => Ok(SpecialSymbol::Plus),
+                "-" => Ok(SpecialSymbol::Minus),
+                "*" => Ok(SpecialSymbol::Multiply),
+                "/" => Ok(SpecialSymbol::Divide),
+                "&" => Ok(SpecialSymbol::Ampersand),
+                "|" => Ok(SpecialSymbol::Pipe),
+                "^" => Ok(SpecialSymbol::Caret),
+                "+=" => Ok(SpecialSymbol::PlusAssign),
+                "-=" => Ok(SpecialSymbol::MinusAssign),
+                "*=" => Ok(SpecialSymbol::MultiplyAssign),
+                "/=" => Ok(SpecialSymbol::DivideAssign),
+                "&=" => Ok(SpecialSymbol::AndAssign),
+                "|=" => Ok(SpecialSymbol::OrAssign),
+                "^=" => Ok(SpecialSymbol::XorAssign),
 
                 _ => Err(SymbolError),
             }
@@ -109,7 +144,7 @@ pub mod lexer {
         }
     }
 
-    #[derive(Debug)]
+    #[derive(Debug, Clone)]
     pub enum Keyword {
         // The `const` keyword
         Const,
@@ -183,16 +218,55 @@ pub mod lexer {
         }
     }
 
-    #[derive(Debug)]
+    impl Display for Keyword {
+        fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+            let s = match self {
+                Keyword::Const => "const",
+                Keyword::Enum => "enum",
+                Keyword::Return => "return",
+                Keyword::New => "new",
+                Keyword::Delete => "delete",
+                Keyword::Include => "include",
+                Keyword::Void => "void",
+                Keyword::Int => "int",
+                Keyword::Double => "double",
+                Keyword::Do => "do",
+                Keyword::For => "for",
+                Keyword::While => "while",
+                Keyword::Break => "break",
+                Keyword::Continue => "continue",
+                Keyword::If => "if",
+                Keyword::Else => "else",
+                Keyword::Switch => "switch",
+                Keyword::Case => "case",
+            };
+            write!(f, "{}", s)
+        }
+    }
+
+    /// The base an [`IntLiteral`](Token::IntLiteral) was written in.
+    #[derive(Debug, Clone, Copy, PartialEq, Eq)]
+    pub enum Radix {
+        Decimal,
+        Hex,
+        Octal,
+        Binary,
+    }
+
+    #[derive(Debug, Clone)]
     pub enum Token {
         Keyword(Keyword),
         // include +-*/&^=
         SpecialSymbol(SpecialSymbol),
         // include ,;
         Comment(String),
-        NumberLiteral(String),
+        IntLiteral { value: i64, radix: Radix },
+        FloatLiteral(f64),
         StringLiteral(String),
         Identifier(String),
+        // the `<...>` path of an `#include <...>`, captured whole while
+        // the lexer is in `Mode::IncludePath`
+        HeaderName(String),
     }
 
     impl Display for Token {
@@ -201,34 +275,146 @@ pub mod lexer {
         }
     }
 
+    /// A `(line, col)` location in the source, both 1-indexed.
+    #[derive(Debug, Clone, Copy, PartialEq, Eq)]
+    pub struct Position {
+        pub line: usize,
+        pub col: usize,
+        // absolute char offset from the start of the buffer
+        pub offset: usize,
+    }
+
+    impl Display for Position {
+        fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+            write!(f, "{}:{}", self.line, self.col)
+        }
+    }
+
+    /// The `[start, end)` source range a token (or error) was produced from.
+    #[derive(Debug, Clone, Copy, PartialEq, Eq)]
+    pub struct Span {
+        pub start: Position,
+        pub end: Position,
+    }
+
+    impl Display for Span {
+        fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+            write!(f, "{}-{}", self.start, self.end)
+        }
+    }
+
+    /// A [`Token`] together with the [`Span`] of source it was lexed from.
     #[derive(Debug, Clone)]
-    pub struct LexerError {
-        details: String,
+    pub struct SpannedToken {
+        pub token: Token,
+        pub span: Span,
     }
 
-    impl From<SymbolError> for LexerError {
-        fn from(symbolError: SymbolError) -> Self {
-            LexerError::new("SymbolError convert to LexerError")
+    impl Display for SpannedToken {
+        fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+            write!(f, "{} @ {}", self.token, self.span)
         }
     }
 
-    impl From<KeywordError> for LexerError {
-        fn from(keywordError: KeywordError) -> Self {
-            LexerError::new("KeywordError convert to LexerError")
+    /// A lexing context. The lexer keeps a stack of these instead of always
+    /// assuming `Normal`, so a handful of constructs where the same
+    /// character means different things (inside a string, inside a block
+    /// comment, inside an `#include <...>` path) each get their own rules,
+    /// and an inner mode's rules take precedence over the outer one it was
+    /// pushed on top of.
+    #[derive(Debug, Clone, Copy, PartialEq, Eq)]
+    pub enum Mode {
+        Normal,
+        // the delimiter (`"` or `'`) we are looking for to close the string
+        InString(char),
+        InBlockComment,
+        // right after `include`, capturing `<...>` as one header-name token
+        IncludePath,
+    }
+
+    /// Wraps the raw character stream and keeps track of where we are in it,
+    /// so every consumed char can be attributed a `Position`.
+    struct Cursor<'a> {
+        buffer: Peekable<Chars<'a>>,
+        line: usize,
+        col: usize,
+        offset: usize,
+        // length (in chars) of each line already fully consumed
+        line_lengths: Vec<usize>,
+    }
+
+    impl<'a> Cursor<'a> {
+        fn new(buffer: &'a str) -> Self {
+            Cursor {
+                buffer: buffer.chars().peekable(),
+                line: 1,
+                col: 1,
+                offset: 0,
+                line_lengths: Vec::new(),
+            }
+        }
+
+        fn position(&self) -> Position {
+            Position {
+                line: self.line,
+                col: self.col,
+                offset: self.offset,
+            }
+        }
+
+        fn next(&mut self) -> Option<char> {
+            let ch = self.buffer.next()?;
+            self.offset += 1;
+            if ch == '\n' {
+                self.line_lengths.push(self.col);
+                self.line += 1;
+                self.col = 1;
+            } else {
+                self.col += 1;
+            }
+            Some(ch)
+        }
+
+        fn peek(&mut self) -> Option<char> {
+            self.buffer.peek().copied()
+        }
+
+        // Maps an absolute char offset back to the (1-indexed) line it
+        // falls on, by walking `line_lengths` (each entry is the number of
+        // chars in that line, newline included) until the running total
+        // passes `offset`. Only meaningful for offsets already consumed --
+        // one still on the current, not-yet-terminated line falls through
+        // to `self.line`.
+        fn line_for_offset(&self, offset: usize) -> usize {
+            let mut consumed = 0;
+            for (i, len) in self.line_lengths.iter().enumerate() {
+                if offset < consumed + len {
+                    return i + 1;
+                }
+                consumed += len;
+            }
+            self.line
         }
     }
 
+    #[derive(Debug, Clone)]
+    pub struct LexerError {
+        details: String,
+        pub position: Position,
+    }
+
     impl LexerError {
-        fn new(msg: &str) -> Self {
+        fn at(msg: &str, position: Position) -> Self {
             Self {
                 details: msg.to_string(),
+                position,
             }
         }
     }
 
     impl fmt::Display for LexerError {
         fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
-            write!(f, "{}", self.details)
+            write!(f, "error at {}: {}", self.position, self.details)
         }
     }
 
@@ -243,20 +429,26 @@ pub mod lexer {
         }
     }
 
-    pub struct Lexer<'a> {
-        pub tokens: Vec<Token>,
-        buffer: Peekable<Chars<'a>>,
-        // true for finish normally
-        status: bool,
-    }
-
-    impl<'a> IntoIterator for Lexer<'a> {
-        type Item = Token;
-        type IntoIter = ::std::vec::IntoIter<Self::Item>;
+    // How many already-produced tokens `unread` can rewind past. Bounded so
+    // the lexer doesn't retain the whole token stream just to support
+    // lookahead.
+    const HISTORY_CAPACITY: usize = 8;
 
-        fn into_iter(self) -> Self::IntoIter {
-            self.tokens.into_iter()
-        }
+    pub struct Lexer<'a> {
+        cursor: Cursor<'a>,
+        // the mode stack; always has at least `Normal` at the bottom
+        modes: Vec<Mode>,
+        // span start recorded just before entering `InString`/`InBlockComment`,
+        // so the mode-dispatched lexing function can still report a span
+        // that covers the opening delimiter
+        pending_start: Option<Position>,
+        // the last `HISTORY_CAPACITY` tokens handed out by `next`, oldest
+        // first, so `unread` can back up into tokens the caller already saw
+        history: VecDeque<Result<SpannedToken, LexerError>>,
+        // how many trailing entries of `history` `unread` has backed up
+        // over; `next` replays those before asking the cursor for anything
+        // new
+        rewound: usize,
     }
 
     // impl<'a> means that it a template
@@ -264,74 +456,422 @@ pub mod lexer {
         // Initialize a Lexer
         pub fn new(buffer: &'a str) -> Lexer<'a> {
             Lexer {
-                tokens: Vec::new(),
-                buffer: buffer.chars().peekable(),
-                status: false,
+                cursor: Cursor::new(buffer),
+                modes: vec![Mode::Normal],
+                pending_start: None,
+                history: VecDeque::with_capacity(HISTORY_CAPACITY),
+                rewound: 0,
             }
         }
 
-        fn next(&mut self) -> Result<char, LexerError> {
-            match self.buffer.next() {
-                Some(ch) => Ok(ch),
-                None => {
-                    self.status = true;
-                    Err(LexerError::new("Finish"))
-                }
+        /// Returns the next token without consuming it.
+        pub fn peek_token(&mut self) -> Option<Result<SpannedToken, LexerError>> {
+            let item = self.next();
+            if item.is_some() {
+                self.unread(1);
             }
+            item
         }
 
-        fn peek(&mut self) -> Option<char> {
-            self.buffer.peek().copied()
+        /// Rewinds the last `n` tokens handed out by `next`, so the next `n`
+        /// calls to `next` replay them instead of lexing fresh input. Capped
+        /// at the bounded history buffer (`HISTORY_CAPACITY`): callers needing
+        /// arbitrary lookahead should buffer tokens themselves rather than
+        /// unread arbitrarily far back.
+        pub fn unread(&mut self, n: usize) {
+            self.rewound = (self.rewound + n).min(self.history.len());
         }
 
-        fn push_token(&mut self, token: Token) {
-            //println!("Token {:?}", token);
-            self.tokens.push(token);
+        fn error(&self, msg: &str) -> LexerError {
+            LexerError::at(msg, self.cursor.position())
         }
 
+        fn current_mode(&self) -> Mode {
+            *self.modes.last().expect("mode stack is never empty")
+        }
+
+        /// Maps an absolute char offset (e.g. [`Position::offset`]) back to
+        /// the (1-indexed) source line it falls on.
+        pub fn line_for_offset(&self, offset: usize) -> usize {
+            self.cursor.line_for_offset(offset)
+        }
+
+        /// Enter a more specific lexing context; its rules take precedence
+        /// over whatever mode was active before, until it is popped.
+        pub fn push_state(&mut self, mode: Mode) {
+            self.modes.push(mode);
+        }
+
+        /// Leave the current mode and return to the one beneath it. The
+        /// base `Normal` mode can never be popped.
+        pub fn pop_state(&mut self) -> Option<Mode> {
+            if self.modes.len() > 1 {
+                self.modes.pop()
+            } else {
+                None
+            }
+        }
+
+        // Like a plain char read, but treats running out of input as an
+        // error: only used once we are partway through a token, where EOF
+        // is never legal (e.g. an unterminated string or comment).
+        fn bump(&mut self) -> Result<char, LexerError> {
+            self.cursor
+                .next()
+                .ok_or_else(|| self.error("unexpected end of input"))
+        }
+
+        fn peek(&mut self) -> Option<char> {
+            self.cursor.peek()
+        }
+
+        // Reads to the end of a `//` comment. Running out of input ends the
+        // comment just like a newline would, rather than being an error --
+        // a file's last line is allowed to end without a trailing `\n`.
         fn get_line(&mut self) -> Result<String, LexerError> {
             let mut buf = String::new();
+            while let Some(ch) = self.cursor.next() {
+                if ch == '\n' {
+                    break;
+                }
+                buf.push(ch);
+            }
+
+            Ok(buf)
+        }
+
+        // Consumes the `<...>` of an `#include <...>` as one header-name
+        // token, so it is never split into `<`, identifiers, `.` and `>`.
+        // `#include "..."` is a plain string literal instead, matching how
+        // the rest of the lexer treats quoted text.
+        // Only entered via `Mode::IncludePath`, pushed right after the
+        // `include` keyword is lexed.
+        fn lex_include_path(&mut self) -> Result<Option<SpannedToken>, LexerError> {
+            while let Some(c) = self.peek() {
+                if c == ' ' || c == '\n' {
+                    self.bump()?;
+                } else {
+                    break;
+                }
+            }
+
+            let start = self.cursor.position();
+            let open = self.bump()?;
+            self.pop_state();
+
+            if open == '"' {
+                let mut s = String::new();
+                loop {
+                    let c = self.bump()?;
+                    if c == '"' {
+                        break;
+                    }
+                    s.push(c);
+                }
+                let span = Span {
+                    start,
+                    end: self.cursor.position(),
+                };
+                return Ok(Some(SpannedToken {
+                    token: Token::StringLiteral(s),
+                    span,
+                }));
+            }
+
+            if open != '<' {
+                return Err(self.error("expected '<' or '\"' to start an #include path"));
+            }
+
+            let mut path = String::new();
+            loop {
+                let c = self.bump()?;
+                if c == '>' {
+                    break;
+                }
+                path.push(c);
+            }
+
+            let span = Span {
+                start,
+                end: self.cursor.position(),
+            };
+            Ok(Some(SpannedToken {
+                token: Token::HeaderName(path),
+                span,
+            }))
+        }
+
+        // Consumes the body of a string/char literal up to the closing
+        // `delim`, decoding escapes as it goes. Only entered via
+        // `Mode::InString`, pushed right after the opening quote is lexed.
+        fn lex_string(&mut self, delim: char) -> Result<Option<SpannedToken>, LexerError> {
+            let start = self
+                .pending_start
+                .take()
+                .unwrap_or_else(|| self.cursor.position());
+            // popped unconditionally so an unterminated literal doesn't
+            // leave the lexer stuck dispatching into `InString` forever
+            let result = self.read_string_body(delim);
+            self.pop_state();
+            let s = result?;
+
+            let span = Span {
+                start,
+                end: self.cursor.position(),
+            };
+            Ok(Some(SpannedToken {
+                token: Token::StringLiteral(s),
+                span,
+            }))
+        }
+
+        fn read_string_body(&mut self, delim: char) -> Result<String, LexerError> {
+            let mut s = String::new();
             loop {
-                let ch = self.next()?;
+                let n = self.bump()?;
+                if n == delim {
+                    break;
+                } else if n == '\\' {
+                    let escaped = self.bump()?;
+                    s.push(self.decode_escape(escaped)?);
+                } else {
+                    s.push(n);
+                }
+            }
+            Ok(s)
+        }
+
+        // Consumes the body of a `/* ... */` comment, up to and including
+        // the closing `*/`. Only entered via `Mode::InBlockComment`, pushed
+        // right after the opening `/*` is lexed.
+        fn lex_block_comment(&mut self) -> Result<Option<SpannedToken>, LexerError> {
+            let start = self
+                .pending_start
+                .take()
+                .unwrap_or_else(|| self.cursor.position());
+            // popped unconditionally, same reasoning as `lex_string`
+            let result = self.read_block_comment_body();
+            self.pop_state();
+            let s = result?;
+
+            let span = Span {
+                start,
+                end: self.cursor.position(),
+            };
+            Ok(Some(SpannedToken {
+                token: Token::Comment(s),
+                span,
+            }))
+        }
+
+        fn read_block_comment_body(&mut self) -> Result<String, LexerError> {
+            let mut s = String::new();
+            let mut state = 0;
+            loop {
+                if state == 2 {
+                    break;
+                }
+                let ch = self.bump()?;
                 match ch {
-                    _ if ch.is_ascii_control() => {
-                        break;
+                    '*' => {
+                        state = 1;
+                    }
+                    '/' => {
+                        if state == 1 {
+                            state = 2;
+                        }
                     }
                     _ => {
-                        buf.push(ch);
+                        if state == 1 {
+                            s.push('*');
+                        }
+                        s.push(ch);
+                        state = 0;
                     }
                 }
             }
+            Ok(s)
+        }
 
-            Ok(buf)
+        // Decodes the character after a `\` inside a string/char literal.
+        fn decode_escape(&mut self, escaped: char) -> Result<char, LexerError> {
+            match escaped {
+                'n' => Ok('\n'),
+                't' => Ok('\t'),
+                'r' => Ok('\r'),
+                '\\' => Ok('\\'),
+                '"' => Ok('"'),
+                '\'' => Ok('\''),
+                '0' => Ok('\0'),
+                // 1 or 2 hex digits, per C's `\xNN`; stop at whatever isn't
+                // hex instead of always eating two chars, or a one-digit
+                // escape right before the closing quote would swallow it
+                'x' => {
+                    let mut hex = String::new();
+                    for _ in 0..2 {
+                        match self.peek() {
+                            Some(c) if c.is_ascii_hexdigit() => {
+                                hex.push(c);
+                                self.bump()?;
+                            }
+                            _ => break,
+                        }
+                    }
+                    if hex.is_empty() {
+                        return Err(self.error("expected a hex digit after \\x"));
+                    }
+                    u8::from_str_radix(&hex, 16)
+                        .map(|b| b as char)
+                        .map_err(|_| self.error("invalid \\x escape"))
+                }
+                other => Err(self.error(&format!("unknown escape sequence '\\{}'", other))),
+            }
         }
 
-        pub fn lex(&mut self) -> Result<(), LexerError> {
+        // Consumes a run of digits valid in `radix`, stopping (without
+        // consuming) at the first character that isn't.
+        fn consume_digits(&mut self, radix: u32) -> String {
+            let mut s = String::new();
+            while let Some(c) = self.peek() {
+                if c.is_digit(radix) {
+                    s.push(c);
+                    self.bump().expect("peek already confirmed a char");
+                } else {
+                    break;
+                }
+            }
+            s
+        }
+
+        // Integer suffixes (`u`, `l`, in any order/case, e.g. `42ul`); we
+        // don't track signedness/width, so they are consumed and discarded.
+        fn consume_int_suffix(&mut self) {
+            while matches!(self.peek(), Some('u') | Some('U') | Some('l') | Some('L')) {
+                self.bump().expect("peek already confirmed a char");
+            }
+        }
+
+        // Float suffixes (`f`, `l`, e.g. `3.14f`); also just discarded.
+        fn consume_float_suffix(&mut self) {
+            while matches!(self.peek(), Some('f') | Some('F') | Some('l') | Some('L')) {
+                self.bump().expect("peek already confirmed a char");
+            }
+        }
+
+        // `first` is the leading digit already consumed by `lex_token`.
+        // Handles `0x`/`0b`/`0`-octal prefixes, a decimal integer or float
+        // (with an optional exponent), and a trailing suffix.
+        fn lex_number(&mut self, first: char) -> Result<Token, LexerError> {
+            if first == '0' {
+                match self.peek() {
+                    Some('x') | Some('X') => {
+                        self.bump()?;
+                        let digits = self.consume_digits(16);
+                        if digits.is_empty() {
+                            return Err(self.error("expected hex digits after '0x'"));
+                        }
+                        self.consume_int_suffix();
+                        let value = i64::from_str_radix(&digits, 16)
+                            .map_err(|_| self.error("invalid hex integer literal"))?;
+                        return Ok(Token::IntLiteral { value, radix: Radix::Hex });
+                    }
+                    Some('b') | Some('B') => {
+                        self.bump()?;
+                        let digits = self.consume_digits(2);
+                        if digits.is_empty() {
+                            return Err(self.error("expected binary digits after '0b'"));
+                        }
+                        self.consume_int_suffix();
+                        let value = i64::from_str_radix(&digits, 2)
+                            .map_err(|_| self.error("invalid binary integer literal"))?;
+                        return Ok(Token::IntLiteral { value, radix: Radix::Binary });
+                    }
+                    Some(c) if c.is_digit(8) => {
+                        let digits = self.consume_digits(8);
+                        // consume_digits(8) stops at the first non-octal
+                        // digit; if that's an 8 or 9 rather than the start
+                        // of a suffix, the literal has an illegal digit
+                        // (e.g. `018`) instead of just ending here
+                        if matches!(self.peek(), Some(d) if d.is_ascii_digit()) {
+                            return Err(self.error("invalid digit in octal integer literal"));
+                        }
+                        self.consume_int_suffix();
+                        let value = i64::from_str_radix(&digits, 8)
+                            .map_err(|_| self.error("invalid octal integer literal"))?;
+                        return Ok(Token::IntLiteral { value, radix: Radix::Octal });
+                    }
+                    _ => {}
+                }
+            }
+
+            let mut num = first.to_string();
+            num.push_str(&self.consume_digits(10));
+
+            let mut is_float = false;
+            if self.peek() == Some('.') {
+                is_float = true;
+                num.push(self.bump()?);
+                num.push_str(&self.consume_digits(10));
+            }
+            if matches!(self.peek(), Some('e') | Some('E')) {
+                is_float = true;
+                num.push(self.bump()?);
+                if matches!(self.peek(), Some('+') | Some('-')) {
+                    num.push(self.bump()?);
+                }
+                let exponent = self.consume_digits(10);
+                if exponent.is_empty() {
+                    return Err(self.error("expected digits in exponent"));
+                }
+                num.push_str(&exponent);
+            }
+
+            if is_float {
+                self.consume_float_suffix();
+                let value: f64 = num.parse().map_err(|_| self.error("invalid float literal"))?;
+                Ok(Token::FloatLiteral(value))
+            } else {
+                // A leading zero followed by more digits and no `.`/exponent
+                // was meant as an octal literal (handled above for digits
+                // 0-7); landing here means one of those digits was 8 or 9,
+                // which is invalid rather than silently decimal.
+                if first == '0' && num.len() > 1 {
+                    return Err(self.error("invalid digit in octal integer literal"));
+                }
+                self.consume_int_suffix();
+                let value: i64 = num.parse().map_err(|_| self.error("invalid integer literal"))?;
+                Ok(Token::IntLiteral { value, radix: Radix::Decimal })
+            }
+        }
+
+        // Lex a single token from the underlying character stream,
+        // skipping spaces and newlines; other whitespace (tabs, `\r`) is
+        // not skipped and is reported as an unexpected symbol.
+        // `Ok(None)` means the input is exhausted.
+        fn lex_token(&mut self) -> Result<Option<SpannedToken>, LexerError> {
             loop {
+                // rules defined for an inner mode take precedence over the
+                // outer one it was pushed on top of
+                match self.current_mode() {
+                    Mode::IncludePath => return self.lex_include_path(),
+                    Mode::InString(delim) => return self.lex_string(delim),
+                    Mode::InBlockComment => return self.lex_block_comment(),
+                    Mode::Normal => {}
+                }
+
                 // once a token
-                let ch = self.next()?;
-                //dbg!(&ch);
-                match ch {
-                    // literal string
+                let start = self.cursor.position();
+                let ch = match self.cursor.next() {
+                    Some(ch) => ch,
+                    None => return Ok(None),
+                };
+                let token = match ch {
+                    // literal string: the body is lexed by `lex_string`,
+                    // dispatched via `Mode::InString` at the top of the loop
                     '"' | '\'' => {
-                        let mut s = String::new();
-                        loop {
-                            match self.next() {
-                                Ok(n) => {
-                                    if ch == n {
-                                        // end of string
-                                        break;
-                                    } else {
-                                        s.push(n);
-                                    }
-                                }
-                                Err(_) => {
-                                    return Err(LexerError::new("Unexpected string ends with EOF"))
-                                }
-                            };
-                        }
-                        // not yet related to position
-                        self.tokens.push(Token::StringLiteral(s));
+                        self.pending_start = Some(start);
+                        self.push_state(Mode::InString(ch));
+                        continue;
                     }
                     // comment or divide
                     '/' => {
@@ -341,88 +881,53 @@ pub mod lexer {
                         // 3. /= as special symbol
                         // 4. // as line comment
                         //let mut s = ch.to_string();
-                        if let Some(n) = self.peek() {
-                            match n {
-                                '*' => {
-                                    // it is a block comment
-                                    let mut s = String::new();
-                                    let mut state = 0;
-                                    'outer: loop {
-                                        if state == 2 {
-                                            break 'outer;
-                                        }
-                                        let ch = self.next()?;
-                                        match ch {
-                                            '*' => {
-                                                state = 1;
-                                            }
-                                            '/' => {
-                                                if state == 1 {
-                                                    state = 2;
-                                                }
-                                            }
-                                            _ => {
-                                                if state == 1 {
-                                                    s.push('*');
-                                                }
-                                                s.push(ch);
-                                                state = 0;
-                                            }
-                                        }
-                                    }
-                                    self.push_token(Token::Comment(s));
-                                }
-                                '=' => {
-                                    let mut s = ch.to_string();
-                                    s.push(n);
-                                    self.push_token(Token::SpecialSymbol(SpecialSymbol::from_str(
-                                        &s,
-                                    )?));
-                                }
-                                '/' => {
-                                    let s = self.get_line()?;
-                                    self.push_token(Token::Comment(s));
-                                }
-                                _ => {
-                                    // a single /
-                                    self.push_token(Token::SpecialSymbol(FromStr::from_str(
-                                        &ch.to_string(),
-                                    )?));
-                                }
+                        match self.peek() {
+                            Some('*') => {
+                                // the body is lexed by `lex_block_comment`,
+                                // dispatched via `Mode::InBlockComment` at
+                                // the top of the loop
+                                self.bump()?; // consume '*'
+                                self.pending_start = Some(start);
+                                self.push_state(Mode::InBlockComment);
+                                continue;
+                            }
+                            Some('=') => {
+                                let n = self.bump()?;
+                                let mut s = ch.to_string();
+                                s.push(n);
+                                let sym = SpecialSymbol::from_str(&s)
+                                    .map_err(|_| self.error("invalid special symbol"))?;
+                                Token::SpecialSymbol(sym)
+                            }
+                            Some('/') => {
+                                let s = self.get_line()?;
+                                Token::Comment(s)
+                            }
+                            // a single /, including a trailing one right at
+                            // EOF, matching every other single-char symbol below
+                            _ => {
+                                let sym = SpecialSymbol::from_str(&ch.to_string())
+                                    .map_err(|_| self.error("invalid special symbol"))?;
+                                Token::SpecialSymbol(sym)
                             }
                         }
                     }
                     // special symbol
-                    '+' | '-' | '*' | '&' | '|' | '^' | '=' => {
+                    '+' | '-' | '*' | '&' | '|' | '^' | '=' | '<' | '>' => {
                         // spcial symbol
                         // 1. alone as special symbol
                         // 2. combine with =
                         let mut s = ch.to_string();
                         if let Some('=') = self.peek() {
-                            s.push('=');
+                            s.push(self.bump()?);
                         }
-                        self.push_token(Token::SpecialSymbol(FromStr::from_str(&s)?));
-                    }
-                    // decimal
-                    _ if ch.is_digit(10) => {
-                        let mut num = ch.to_string();
-                        while self.peek().unwrap().is_digit(10) {
-                            num.push(self.next()?);
-                        }
-
-                        if self.peek() == Some('.') {
-                            num.push('.');
-                            while let Some(n) = self.peek() {
-                                if n.is_numeric() {
-                                    num.push(self.next()?);
-                                } else {
-                                    break;
-                                }
-                            }
-                        }
-
-                        self.push_token(Token::NumberLiteral(num));
+                        let sym = SpecialSymbol::from_str(&s)
+                            .map_err(|_| self.error("invalid special symbol"))?;
+                        Token::SpecialSymbol(sym)
                     }
+                    // number: decimal, or 0x/0b/0-octal, with an optional
+                    // float part and a trailing suffix
+                    _ if ch.is_ascii_digit() => self.lex_number(ch)?,
                     // identifier or keyword
                     _ if ch.is_alphabetic() => {
                         // it is a
@@ -431,8 +936,7 @@ pub mod lexer {
                         let mut s = ch.to_string();
                         while let Some(c) = self.peek() {
                             if c.is_ascii_alphabetic() || c.is_ascii_digit() || c == '.' {
-                                let c = self.next()?;
-                                s.push(c);
+                                s.push(self.bump()?);
                             } else {
                                 break;
                             }
@@ -440,28 +944,139 @@ pub mod lexer {
 
                         if let Ok(keyword) = Keyword::from_str(&s) {
                             //println!("Keyword {:?}", keyword);
-                            self.push_token(Token::Keyword(keyword));
+                            if matches!(keyword, Keyword::Include) {
+                                // the following `<...>` is a single
+                                // header-name token, not `<`, identifiers,
+                                // `.` and `>`
+                                self.push_state(Mode::IncludePath);
+                            }
+                            Token::Keyword(keyword)
                         } else {
                             //println!("identifier {:?}", s);
-                            self.push_token(Token::Identifier(s));
+                            Token::Identifier(s)
                         }
                     }
-                    ' ' | '\n' => (),
+                    ' ' | '\n' => continue,
                     // other special symbol
                     _ => {
                         if let Ok(sym) = FromStr::from_str(&ch.to_string()) {
-                            self.push_token(Token::SpecialSymbol(sym));
+                            Token::SpecialSymbol(sym)
                         } else {
-                            dbg!(&ch);
-                            return Err(LexerError::new("Unexpected symbol in the sequence"));
+                            return Err(self.error("Unexpected symbol in the sequence"));
                         }
                     }
+                };
+
+                let span = Span {
+                    start,
+                    end: self.cursor.position(),
+                };
+                return Ok(Some(SpannedToken { token, span }));
+            }
+        }
+
+    }
+
+    impl<'a> Iterator for Lexer<'a> {
+        type Item = Result<SpannedToken, LexerError>;
+
+        fn next(&mut self) -> Option<Self::Item> {
+            if self.rewound > 0 {
+                let idx = self.history.len() - self.rewound;
+                self.rewound -= 1;
+                return Some(self.history[idx].clone());
+            }
+
+            let item = match self.lex_token() {
+                Ok(None) => return None,
+                Ok(Some(token)) => Ok(token),
+                Err(e) => Err(e),
+            };
+
+            if self.history.len() == HISTORY_CAPACITY {
+                self.history.pop_front();
+            }
+            self.history.push_back(item.clone());
+            Some(item)
+        }
+    }
+
+    #[cfg(test)]
+    mod tests {
+        use super::*;
+
+        #[test]
+        fn line_for_offset_maps_offsets_back_to_lines() {
+            let mut lexer = Lexer::new("int a;\nint b;\nint c;");
+            let tokens: Vec<SpannedToken> = lexer.by_ref().map(|t| t.unwrap()).collect();
+
+            // "int c;" starts after two 7-char lines ("int a;\n" etc.)
+            let c_decl = &tokens[tokens.len() - 3];
+            assert_eq!(lexer.line_for_offset(c_decl.span.start.offset), 3);
+
+            let a_decl = &tokens[0];
+            assert_eq!(lexer.line_for_offset(a_decl.span.start.offset), 1);
+        }
+
+        fn lex_one(src: &str) -> Token {
+            Lexer::new(src).next().unwrap().unwrap().token
+        }
+
+        #[test]
+        fn hex_escape_accepts_one_or_two_digits() {
+            match lex_one(r#""\xA""#) {
+                Token::StringLiteral(s) => assert_eq!(s, "\u{a}"),
+                other => panic!("expected a string literal, got {:?}", other),
+            }
+            match lex_one(r#""\x41""#) {
+                Token::StringLiteral(s) => assert_eq!(s, "A"),
+                other => panic!("expected a string literal, got {:?}", other),
+            }
+        }
+
+        #[test]
+        fn invalid_octal_digit_is_rejected() {
+            assert!(matches!(Lexer::new("09").next(), Some(Err(_))));
+            assert!(matches!(Lexer::new("08").next(), Some(Err(_))));
+            // `01` lexes fine as octal, but the trailing `8` is an illegal
+            // digit rather than the start of a second number
+            assert!(matches!(Lexer::new("018").next(), Some(Err(_))));
+        }
+
+        #[test]
+        fn leading_zero_octal_digits_still_lex() {
+            match lex_one("010") {
+                Token::IntLiteral { value, radix } => {
+                    assert_eq!(value, 8);
+                    assert_eq!(radix, Radix::Octal);
                 }
+                other => panic!("expected an int literal, got {:?}", other),
             }
         }
 
-        pub fn get_status(&self) -> bool {
-            return self.status;
+        #[test]
+        fn peek_token_does_not_consume() {
+            let mut lexer = Lexer::new("a b");
+            let peeked = lexer.peek_token().unwrap().unwrap();
+            assert!(matches!(peeked.token, Token::Identifier(ref name) if name == "a"));
+            let next = lexer.next().unwrap().unwrap();
+            assert!(matches!(next.token, Token::Identifier(ref name) if name == "a"));
+        }
+
+        #[test]
+        fn unread_replays_already_produced_tokens() {
+            let mut lexer = Lexer::new("a b c");
+            let a = lexer.next().unwrap().unwrap();
+            let b = lexer.next().unwrap().unwrap();
+            lexer.unread(2);
+
+            let replayed_a = lexer.next().unwrap().unwrap();
+            let replayed_b = lexer.next().unwrap().unwrap();
+            assert!(matches!((&a.token, &replayed_a.token), (Token::Identifier(x), Token::Identifier(y)) if x == y));
+            assert!(matches!((&b.token, &replayed_b.token), (Token::Identifier(x), Token::Identifier(y)) if x == y));
+
+            let c = lexer.next().unwrap().unwrap();
+            assert!(matches!(c.token, Token::Identifier(name) if name == "c"));
         }
     }
 
@@ -0,0 +1,7 @@
+pub mod lexer;
+pub mod parser;
+pub mod preprocessor;
+
+pub use lexer::lexer::*;
+pub use parser::parser::*;
+pub use preprocessor::preprocessor::*;
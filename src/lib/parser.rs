@@ -0,0 +1,674 @@
+pub mod parser {
+    use super::super::lexer::lexer::{Keyword, LexerError, SpecialSymbol, SpannedToken, Token};
+    use std::error::Error;
+    use std::fmt::{self, Display, Formatter};
+
+    #[derive(Debug, Clone)]
+    pub enum Expr {
+        IntLiteral(i64),
+        FloatLiteral(f64),
+        StringLiteral(String),
+        Identifier(String),
+        Call {
+            callee: String,
+            args: Vec<Expr>,
+        },
+        Unary {
+            op: SpecialSymbol,
+            expr: Box<Expr>,
+        },
+        Binary {
+            op: SpecialSymbol,
+            left: Box<Expr>,
+            right: Box<Expr>,
+        },
+    }
+
+    #[derive(Debug, Clone)]
+    pub struct Param {
+        pub type_name: String,
+        pub name: String,
+    }
+
+    // A `case <value>:` arm of a `switch`.
+    #[derive(Debug, Clone)]
+    pub struct Case {
+        pub value: Expr,
+        pub body: Vec<Node>,
+    }
+
+    #[derive(Debug, Clone)]
+    pub enum Node {
+        FunctionDeclaration {
+            return_type: String,
+            name: String,
+            params: Vec<Param>,
+            body: Vec<Node>,
+        },
+        VariableDeclaration {
+            type_name: String,
+            name: String,
+            init: Option<Expr>,
+        },
+        If {
+            cond: Expr,
+            then_branch: Vec<Node>,
+            else_branch: Option<Vec<Node>>,
+        },
+        While {
+            cond: Expr,
+            body: Vec<Node>,
+        },
+        Do {
+            body: Vec<Node>,
+            cond: Expr,
+        },
+        For {
+            init: Option<Box<Node>>,
+            cond: Option<Expr>,
+            step: Option<Expr>,
+            body: Vec<Node>,
+        },
+        Switch {
+            cond: Expr,
+            cases: Vec<Case>,
+        },
+        Return(Option<Expr>),
+        Break,
+        Continue,
+        Block(Vec<Node>),
+        ExprStatement(Expr),
+    }
+
+    // Wraps the token the parser was looking at when it gave up, so callers
+    // can point at the offending source location via its span.
+    #[derive(Debug)]
+    pub struct ParseError {
+        pub message: String,
+        pub found: Option<SpannedToken>,
+    }
+
+    impl Display for ParseError {
+        fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+            match &self.found {
+                Some(tok) => write!(
+                    f,
+                    "parse error at {}: {} (found {})",
+                    tok.span.start, self.message, tok.token
+                ),
+                None => write!(f, "parse error at end of input: {}", self.message),
+            }
+        }
+    }
+
+    impl Error for ParseError {
+        fn description(&self) -> &str {
+            &self.message
+        }
+
+        fn cause(&self) -> Option<&dyn Error> {
+            None
+        }
+    }
+
+    impl From<LexerError> for ParseError {
+        fn from(e: LexerError) -> Self {
+            ParseError {
+                message: e.to_string(),
+                found: None,
+            }
+        }
+    }
+
+    fn is_type_keyword(keyword: &Keyword) -> bool {
+        matches!(
+            keyword,
+            Keyword::Int | Keyword::Void | Keyword::Double | Keyword::Const
+        )
+    }
+
+    // Binding power (and associativity) of a binary operator, loosest first.
+    // `parse_expr` climbs this ladder via precedence climbing.
+    fn binding_power(sym: &SpecialSymbol) -> Option<(u8, bool)> {
+        use SpecialSymbol::*;
+        Some(match sym {
+            Assign => (1, true),
+            Equal | GreaterOrEqual | SmallerOrEqual | LeftAngleBracket | RightAngleBracket => {
+                (2, false)
+            }
+            Pipe => (3, false),
+            Caret => (4, false),
+            Ampersand => (5, false),
+            Plus | Minus => (6, false),
+            Multiply | Divide => (7, false),
+            _ => return None,
+        })
+    }
+
+    /// A recursive-descent parser over any token stream (a [`Lexer`] or a
+    /// preprocessed `Vec<SpannedToken>`), with a single token of lookahead.
+    ///
+    /// [`Lexer`]: super::super::lexer::lexer::Lexer
+    pub struct Parser<I: Iterator<Item = Result<SpannedToken, LexerError>>> {
+        tokens: I,
+        current: Option<SpannedToken>,
+    }
+
+    impl<I: Iterator<Item = Result<SpannedToken, LexerError>>> Parser<I> {
+        pub fn new(tokens: I) -> Self {
+            Parser {
+                tokens,
+                current: None,
+            }
+        }
+
+        pub fn parse(&mut self) -> Result<Vec<Node>, ParseError> {
+            self.bump()?;
+            let mut nodes = Vec::new();
+            while self.current.is_some() {
+                nodes.push(self.parse_declaration()?);
+            }
+            Ok(nodes)
+        }
+
+        // Pulls the next token off the token stream into `self.current`.
+        fn bump(&mut self) -> Result<(), ParseError> {
+            self.current = match self.tokens.next() {
+                Some(Ok(tok)) => Some(tok),
+                Some(Err(e)) => return Err(e.into()),
+                None => None,
+            };
+            Ok(())
+        }
+
+        fn error(&self, message: &str) -> ParseError {
+            ParseError {
+                message: message.to_string(),
+                found: self.current.clone(),
+            }
+        }
+
+        fn peek_token(&self) -> Option<&Token> {
+            self.current.as_ref().map(|t| &t.token)
+        }
+
+        fn peek_is(&self, pred: impl Fn(&Token) -> bool) -> bool {
+            self.current.as_ref().map_or(false, |t| pred(&t.token))
+        }
+
+        // Consumes the current token if it matches `pred`, advancing the
+        // lookahead; otherwise reports `what` as the expected construct.
+        fn expect(&mut self, pred: impl Fn(&Token) -> bool, what: &str) -> Result<SpannedToken, ParseError> {
+            if !self.peek_is(pred) {
+                return Err(self.error(&format!("expected {}", what)));
+            }
+            let tok = self.current.take().expect("peek_is confirmed a token");
+            self.bump()?;
+            Ok(tok)
+        }
+
+        fn expect_left_paren(&mut self) -> Result<(), ParseError> {
+            self.expect(
+                |t| matches!(t, Token::SpecialSymbol(SpecialSymbol::LeftParenthesis)),
+                "'('",
+            )
+            .map(|_| ())
+        }
+
+        fn expect_right_paren(&mut self) -> Result<(), ParseError> {
+            self.expect(
+                |t| matches!(t, Token::SpecialSymbol(SpecialSymbol::RightParenthesis)),
+                "')'",
+            )
+            .map(|_| ())
+        }
+
+        fn expect_left_brace(&mut self) -> Result<(), ParseError> {
+            self.expect(
+                |t| matches!(t, Token::SpecialSymbol(SpecialSymbol::LeftBrace)),
+                "'{'",
+            )
+            .map(|_| ())
+        }
+
+        fn expect_right_brace(&mut self) -> Result<(), ParseError> {
+            self.expect(
+                |t| matches!(t, Token::SpecialSymbol(SpecialSymbol::RightBrace)),
+                "'}'",
+            )
+            .map(|_| ())
+        }
+
+        fn expect_semicolon(&mut self) -> Result<(), ParseError> {
+            self.expect(
+                |t| matches!(t, Token::SpecialSymbol(SpecialSymbol::Semicolon)),
+                "';'",
+            )
+            .map(|_| ())
+        }
+
+        fn expect_identifier(&mut self) -> Result<String, ParseError> {
+            let tok = self.expect(|t| matches!(t, Token::Identifier(_)), "an identifier")?;
+            match tok.token {
+                Token::Identifier(name) => Ok(name),
+                _ => unreachable!("expect() already checked this is an identifier"),
+            }
+        }
+
+        fn parse_type_name(&mut self) -> Result<String, ParseError> {
+            match self.peek_token() {
+                Some(Token::Keyword(k)) if is_type_keyword(k) => {
+                    let name = k.to_string();
+                    self.bump()?;
+                    Ok(name)
+                }
+                Some(Token::Identifier(name)) => {
+                    let name = name.clone();
+                    self.bump()?;
+                    Ok(name)
+                }
+                _ => Err(self.error("expected a type name")),
+            }
+        }
+
+        // A top-level or block-local `<type> <name> [= <expr>];` or
+        // `<type> <name>(<params>) { ... }`. A function prototype without a
+        // body is not accepted: a `(` after the name always requires a block.
+        fn parse_declaration(&mut self) -> Result<Node, ParseError> {
+            let type_name = self.parse_type_name()?;
+            let name = self.expect_identifier()?;
+
+            if self.peek_is(|t| matches!(t, Token::SpecialSymbol(SpecialSymbol::LeftParenthesis))) {
+                self.bump()?;
+                let params = self.parse_params()?;
+                self.expect_right_paren()?;
+                let body = self.parse_block()?;
+                Ok(Node::FunctionDeclaration {
+                    return_type: type_name,
+                    name,
+                    params,
+                    body,
+                })
+            } else {
+                let init = if self.peek_is(|t| matches!(t, Token::SpecialSymbol(SpecialSymbol::Assign))) {
+                    self.bump()?;
+                    Some(self.parse_expr(0)?)
+                } else {
+                    None
+                };
+                self.expect_semicolon()?;
+                Ok(Node::VariableDeclaration {
+                    type_name,
+                    name,
+                    init,
+                })
+            }
+        }
+
+        fn parse_params(&mut self) -> Result<Vec<Param>, ParseError> {
+            let mut params = Vec::new();
+            if self.peek_is(|t| matches!(t, Token::SpecialSymbol(SpecialSymbol::RightParenthesis))) {
+                return Ok(params);
+            }
+            loop {
+                let type_name = self.parse_type_name()?;
+                let name = self.expect_identifier()?;
+                params.push(Param { type_name, name });
+                if self.peek_is(|t| matches!(t, Token::SpecialSymbol(SpecialSymbol::Comma))) {
+                    self.bump()?;
+                } else {
+                    break;
+                }
+            }
+            Ok(params)
+        }
+
+        fn parse_block(&mut self) -> Result<Vec<Node>, ParseError> {
+            self.expect_left_brace()?;
+            let mut body = Vec::new();
+            while !self.peek_is(|t| matches!(t, Token::SpecialSymbol(SpecialSymbol::RightBrace))) {
+                if self.current.is_none() {
+                    return Err(self.error("unexpected end of input inside a block"));
+                }
+                body.push(self.parse_statement()?);
+            }
+            self.expect_right_brace()?;
+            Ok(body)
+        }
+
+        // A single statement, or `{ ... }` as a list of statements -- used
+        // wherever C allows either a bare statement or a braced block.
+        fn parse_statement_or_block(&mut self) -> Result<Vec<Node>, ParseError> {
+            if self.peek_is(|t| matches!(t, Token::SpecialSymbol(SpecialSymbol::LeftBrace))) {
+                self.parse_block()
+            } else {
+                Ok(vec![self.parse_statement()?])
+            }
+        }
+
+        fn parse_statement(&mut self) -> Result<Node, ParseError> {
+            match self.peek_token() {
+                Some(Token::Keyword(Keyword::If)) => self.parse_if(),
+                Some(Token::Keyword(Keyword::While)) => self.parse_while(),
+                Some(Token::Keyword(Keyword::Do)) => self.parse_do(),
+                Some(Token::Keyword(Keyword::For)) => self.parse_for(),
+                Some(Token::Keyword(Keyword::Return)) => self.parse_return(),
+                Some(Token::Keyword(Keyword::Switch)) => self.parse_switch(),
+                Some(Token::Keyword(Keyword::Break)) => {
+                    self.bump()?;
+                    self.expect_semicolon()?;
+                    Ok(Node::Break)
+                }
+                Some(Token::Keyword(Keyword::Continue)) => {
+                    self.bump()?;
+                    self.expect_semicolon()?;
+                    Ok(Node::Continue)
+                }
+                Some(Token::Keyword(k)) if is_type_keyword(k) => self.parse_declaration(),
+                Some(Token::SpecialSymbol(SpecialSymbol::LeftBrace)) => {
+                    Ok(Node::Block(self.parse_block()?))
+                }
+                _ => {
+                    let expr = self.parse_expr(0)?;
+                    self.expect_semicolon()?;
+                    Ok(Node::ExprStatement(expr))
+                }
+            }
+        }
+
+        fn parse_if(&mut self) -> Result<Node, ParseError> {
+            self.bump()?; // `if`
+            self.expect_left_paren()?;
+            let cond = self.parse_expr(0)?;
+            self.expect_right_paren()?;
+            let then_branch = self.parse_statement_or_block()?;
+            let else_branch = if self.peek_is(|t| matches!(t, Token::Keyword(Keyword::Else))) {
+                self.bump()?;
+                Some(self.parse_statement_or_block()?)
+            } else {
+                None
+            };
+            Ok(Node::If {
+                cond,
+                then_branch,
+                else_branch,
+            })
+        }
+
+        fn parse_while(&mut self) -> Result<Node, ParseError> {
+            self.bump()?; // `while`
+            self.expect_left_paren()?;
+            let cond = self.parse_expr(0)?;
+            self.expect_right_paren()?;
+            let body = self.parse_statement_or_block()?;
+            Ok(Node::While { cond, body })
+        }
+
+        fn parse_do(&mut self) -> Result<Node, ParseError> {
+            self.bump()?; // `do`
+            let body = self.parse_statement_or_block()?;
+            self.expect(|t| matches!(t, Token::Keyword(Keyword::While)), "'while'")?;
+            self.expect_left_paren()?;
+            let cond = self.parse_expr(0)?;
+            self.expect_right_paren()?;
+            self.expect_semicolon()?;
+            Ok(Node::Do { body, cond })
+        }
+
+        fn parse_for(&mut self) -> Result<Node, ParseError> {
+            self.bump()?; // `for`
+            self.expect_left_paren()?;
+
+            let init = if self.peek_is(|t| matches!(t, Token::SpecialSymbol(SpecialSymbol::Semicolon))) {
+                self.bump()?;
+                None
+            } else {
+                Some(Box::new(self.parse_for_init()?))
+            };
+
+            let cond = if self.peek_is(|t| matches!(t, Token::SpecialSymbol(SpecialSymbol::Semicolon))) {
+                None
+            } else {
+                Some(self.parse_expr(0)?)
+            };
+            self.expect_semicolon()?;
+
+            let step = if self.peek_is(|t| matches!(t, Token::SpecialSymbol(SpecialSymbol::RightParenthesis))) {
+                None
+            } else {
+                Some(self.parse_expr(0)?)
+            };
+            self.expect_right_paren()?;
+
+            let body = self.parse_statement_or_block()?;
+            Ok(Node::For {
+                init,
+                cond,
+                step,
+                body,
+            })
+        }
+
+        // The `for (<init>; ...)` clause: either a variable declaration or
+        // a bare expression statement, both of which consume their `;`.
+        fn parse_for_init(&mut self) -> Result<Node, ParseError> {
+            if let Some(Token::Keyword(k)) = self.peek_token() {
+                if is_type_keyword(k) {
+                    return self.parse_declaration();
+                }
+            }
+            let expr = self.parse_expr(0)?;
+            self.expect_semicolon()?;
+            Ok(Node::ExprStatement(expr))
+        }
+
+        fn parse_return(&mut self) -> Result<Node, ParseError> {
+            self.bump()?; // `return`
+            let value = if self.peek_is(|t| matches!(t, Token::SpecialSymbol(SpecialSymbol::Semicolon))) {
+                None
+            } else {
+                Some(self.parse_expr(0)?)
+            };
+            self.expect_semicolon()?;
+            Ok(Node::Return(value))
+        }
+
+        fn parse_switch(&mut self) -> Result<Node, ParseError> {
+            self.bump()?; // `switch`
+            self.expect_left_paren()?;
+            let cond = self.parse_expr(0)?;
+            self.expect_right_paren()?;
+            self.expect_left_brace()?;
+
+            let mut cases = Vec::new();
+            while !self.peek_is(|t| matches!(t, Token::SpecialSymbol(SpecialSymbol::RightBrace))) {
+                self.expect(|t| matches!(t, Token::Keyword(Keyword::Case)), "'case'")?;
+                let value = self.parse_expr(0)?;
+                self.expect(
+                    |t| matches!(t, Token::SpecialSymbol(SpecialSymbol::Colon)),
+                    "':'",
+                )?;
+                let mut body = Vec::new();
+                while !self.peek_is(|t| {
+                    matches!(t, Token::Keyword(Keyword::Case))
+                        || matches!(t, Token::SpecialSymbol(SpecialSymbol::RightBrace))
+                }) {
+                    body.push(self.parse_statement()?);
+                }
+                cases.push(Case { value, body });
+            }
+            self.expect_right_brace()?;
+            Ok(Node::Switch { cond, cases })
+        }
+
+        // Precedence climbing: `min_bp` is the loosest binding power an
+        // operator encountered here is allowed to have.
+        fn parse_expr(&mut self, min_bp: u8) -> Result<Expr, ParseError> {
+            let mut left = self.parse_unary()?;
+
+            loop {
+                let op = match self.peek_token() {
+                    Some(Token::SpecialSymbol(sym)) => sym.clone(),
+                    _ => break,
+                };
+                let (bp, right_assoc) = match binding_power(&op) {
+                    Some(bp) => bp,
+                    None => break,
+                };
+                if bp < min_bp {
+                    break;
+                }
+                self.bump()?;
+                let next_min_bp = if right_assoc { bp } else { bp + 1 };
+                let right = self.parse_expr(next_min_bp)?;
+                left = Expr::Binary {
+                    op,
+                    left: Box::new(left),
+                    right: Box::new(right),
+                };
+            }
+
+            Ok(left)
+        }
+
+        fn parse_unary(&mut self) -> Result<Expr, ParseError> {
+            if let Some(Token::SpecialSymbol(sym)) = self.peek_token() {
+                if matches!(sym, SpecialSymbol::Plus | SpecialSymbol::Minus) {
+                    let op = sym.clone();
+                    self.bump()?;
+                    let expr = self.parse_unary()?;
+                    return Ok(Expr::Unary {
+                        op,
+                        expr: Box::new(expr),
+                    });
+                }
+            }
+            self.parse_primary()
+        }
+
+        fn parse_primary(&mut self) -> Result<Expr, ParseError> {
+            let tok = match self.current.take() {
+                Some(tok) => tok,
+                None => return Err(self.error("expected an expression")),
+            };
+            self.bump()?;
+
+            match tok.token {
+                Token::IntLiteral { value, .. } => Ok(Expr::IntLiteral(value)),
+                Token::FloatLiteral(value) => Ok(Expr::FloatLiteral(value)),
+                Token::StringLiteral(s) => Ok(Expr::StringLiteral(s)),
+                Token::Identifier(name) => {
+                    if self.peek_is(|t| matches!(t, Token::SpecialSymbol(SpecialSymbol::LeftParenthesis))) {
+                        self.bump()?;
+                        let args = self.parse_args()?;
+                        self.expect_right_paren()?;
+                        Ok(Expr::Call { callee: name, args })
+                    } else {
+                        Ok(Expr::Identifier(name))
+                    }
+                }
+                Token::SpecialSymbol(SpecialSymbol::LeftParenthesis) => {
+                    let expr = self.parse_expr(0)?;
+                    self.expect_right_paren()?;
+                    Ok(expr)
+                }
+                _ => Err(ParseError {
+                    message: "expected an expression".to_string(),
+                    found: Some(tok),
+                }),
+            }
+        }
+
+        fn parse_args(&mut self) -> Result<Vec<Expr>, ParseError> {
+            let mut args = Vec::new();
+            if self.peek_is(|t| matches!(t, Token::SpecialSymbol(SpecialSymbol::RightParenthesis))) {
+                return Ok(args);
+            }
+            loop {
+                args.push(self.parse_expr(0)?);
+                if self.peek_is(|t| matches!(t, Token::SpecialSymbol(SpecialSymbol::Comma))) {
+                    self.bump()?;
+                } else {
+                    break;
+                }
+            }
+            Ok(args)
+        }
+    }
+
+    #[cfg(test)]
+    mod tests {
+        use super::*;
+        use super::super::super::lexer::lexer::Lexer;
+
+        fn parse(src: &str) -> Result<Vec<Node>, ParseError> {
+            Parser::new(Lexer::new(src)).parse()
+        }
+
+        fn parse_one_expr(src: &str) -> Expr {
+            let nodes = parse(&format!("void f() {{ {}; }}", src)).expect("should parse");
+            match &nodes[0] {
+                Node::FunctionDeclaration { body, .. } => match &body[0] {
+                    Node::ExprStatement(expr) => expr.clone(),
+                    other => panic!("expected an expression statement, got {:?}", other),
+                },
+                other => panic!("expected a function declaration, got {:?}", other),
+            }
+        }
+
+        #[test]
+        fn assignment_is_right_associative() {
+            // `a = b = c` should parse as `a = (b = c)`, not `(a = b) = c`.
+            match parse_one_expr("a = b = c") {
+                Expr::Binary { op: SpecialSymbol::Assign, left, right } => {
+                    assert!(matches!(*left, Expr::Identifier(name) if name == "a"));
+                    assert!(matches!(
+                        *right,
+                        Expr::Binary { op: SpecialSymbol::Assign, .. }
+                    ));
+                }
+                other => panic!("expected an assignment, got {:?}", other),
+            }
+        }
+
+        #[test]
+        fn multiplication_binds_tighter_than_addition_and_comparison() {
+            // `a + b * c == d` should parse as `(a + (b * c)) == d`.
+            match parse_one_expr("a + b * c == d") {
+                Expr::Binary { op: SpecialSymbol::Equal, left, right } => {
+                    assert!(matches!(*right, Expr::Identifier(name) if name == "d"));
+                    match *left {
+                        Expr::Binary { op: SpecialSymbol::Plus, left, right } => {
+                            assert!(matches!(*left, Expr::Identifier(name) if name == "a"));
+                            assert!(matches!(*right, Expr::Binary { op: SpecialSymbol::Multiply, .. }));
+                        }
+                        other => panic!("expected `a + b * c`, got {:?}", other),
+                    }
+                }
+                other => panic!("expected an equality comparison, got {:?}", other),
+            }
+        }
+
+        #[test]
+        fn function_with_params_and_body_parses() {
+            let nodes = parse("int add(int a, int b) { return a + b; }").expect("should parse");
+            match &nodes[0] {
+                Node::FunctionDeclaration { return_type, name, params, body } => {
+                    assert_eq!(return_type, "int");
+                    assert_eq!(name, "add");
+                    assert_eq!(params.len(), 2);
+                    assert_eq!(params[0].name, "a");
+                    assert_eq!(params[1].name, "b");
+                    assert!(matches!(body[0], Node::Return(Some(_))));
+                }
+                other => panic!("expected a function declaration, got {:?}", other),
+            }
+        }
+
+        #[test]
+        fn missing_semicolon_is_a_parse_error() {
+            let err = parse("int a = 1").unwrap_err();
+            assert!(err.message.contains("';'"));
+        }
+    }
+}
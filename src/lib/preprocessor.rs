@@ -0,0 +1,479 @@
+pub mod preprocessor {
+    use super::super::lexer::lexer::{Lexer, LexerError, SpecialSymbol, SpannedToken, Token};
+    use std::collections::HashMap;
+    use std::error::Error;
+    use std::fmt::{self, Display, Formatter};
+    use std::path::PathBuf;
+
+    // A registered `#define`. `Object` is substituted verbatim; `Function`
+    // is only expanded when its name is followed by a `(...)` call, with
+    // the call's arguments substituted positionally into `body`.
+    #[derive(Debug, Clone)]
+    enum Macro {
+        Object(Vec<SpannedToken>),
+        Function {
+            params: Vec<String>,
+            body: Vec<SpannedToken>,
+        },
+    }
+
+    #[derive(Debug)]
+    pub struct PreprocessError {
+        pub message: String,
+    }
+
+    impl PreprocessError {
+        fn new(message: impl Into<String>) -> Self {
+            PreprocessError {
+                message: message.into(),
+            }
+        }
+    }
+
+    impl Display for PreprocessError {
+        fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+            write!(f, "preprocessor error: {}", self.message)
+        }
+    }
+
+    impl Error for PreprocessError {
+        fn description(&self) -> &str {
+            &self.message
+        }
+
+        fn cause(&self) -> Option<&dyn Error> {
+            // Generic error, underlying cause isn't tracked.
+            None
+        }
+    }
+
+    impl From<LexerError> for PreprocessError {
+        fn from(e: LexerError) -> Self {
+            PreprocessError::new(e.to_string())
+        }
+    }
+
+    // One level of `#ifdef`/`#ifndef` nesting. `#else` flips `branch_active`;
+    // a region is only kept when every enclosing frame is active, so nesting
+    // inside an already-inactive frame just stays inactive regardless of its
+    // own condition.
+    struct CondFrame {
+        branch_active: bool,
+    }
+
+    /// Resolves `#include`, `#define` and `#ifdef`/`#ifndef`/`#else`/`#endif`
+    /// directives, running as a pass between the [`Lexer`] and the
+    /// [`Parser`](super::super::parser::parser::Parser). Each directive is
+    /// handled as a small state machine keyed off the leading `#` token.
+    pub struct Preprocessor {
+        defines: HashMap<String, Macro>,
+        include_paths: Vec<PathBuf>,
+        // files currently being included, innermost last, to catch cycles
+        include_stack: Vec<PathBuf>,
+    }
+
+    impl Preprocessor {
+        pub fn new(include_paths: Vec<PathBuf>) -> Self {
+            Preprocessor {
+                defines: HashMap::new(),
+                include_paths,
+                include_stack: Vec::new(),
+            }
+        }
+
+        /// Runs every directive in `tokens` and returns the token stream the
+        /// parser should actually see.
+        pub fn process(&mut self, tokens: Vec<SpannedToken>) -> Result<Vec<SpannedToken>, PreprocessError> {
+            let mut out = Vec::new();
+            let mut cond_stack: Vec<CondFrame> = Vec::new();
+            let mut active_macros: Vec<String> = Vec::new();
+            let mut i = 0;
+
+            while i < tokens.len() {
+                if matches!(tokens[i].token, Token::SpecialSymbol(SpecialSymbol::Sharp)) {
+                    let line = tokens[i].span.start.line;
+                    let mut j = i + 1;
+                    while j < tokens.len() && tokens[j].span.start.line == line {
+                        j += 1;
+                    }
+                    self.handle_directive(&tokens[i + 1..j], &mut cond_stack, &mut out)?;
+                    i = j;
+                } else if cond_stack.iter().all(|f| f.branch_active) {
+                    self.expand_and_push(&tokens, &mut i, &mut active_macros, &mut out)?;
+                } else {
+                    i += 1;
+                }
+            }
+
+            if !cond_stack.is_empty() {
+                return Err(PreprocessError::new(
+                    "unterminated conditional: missing #endif",
+                ));
+            }
+
+            Ok(out)
+        }
+
+        // `directive` is everything on the `#` line after the `#` itself.
+        fn handle_directive(
+            &mut self,
+            directive: &[SpannedToken],
+            cond_stack: &mut Vec<CondFrame>,
+            out: &mut Vec<SpannedToken>,
+        ) -> Result<(), PreprocessError> {
+            let head = directive
+                .first()
+                .ok_or_else(|| PreprocessError::new("empty preprocessor directive"))?;
+            let rest = &directive[1..];
+            let active = cond_stack.iter().all(|f| f.branch_active);
+
+            match &head.token {
+                Token::Keyword(super::super::lexer::lexer::Keyword::Include) => {
+                    if active {
+                        self.handle_include(rest, out)?;
+                    }
+                }
+                Token::Identifier(name) if name == "define" => {
+                    if active {
+                        self.handle_define(rest)?;
+                    }
+                }
+                Token::Identifier(name) if name == "ifdef" => {
+                    let cond_name = Self::expect_identifier(rest, "ifdef")?;
+                    cond_stack.push(CondFrame {
+                        branch_active: self.defines.contains_key(&cond_name),
+                    });
+                }
+                Token::Identifier(name) if name == "ifndef" => {
+                    let cond_name = Self::expect_identifier(rest, "ifndef")?;
+                    cond_stack.push(CondFrame {
+                        branch_active: !self.defines.contains_key(&cond_name),
+                    });
+                }
+                Token::Keyword(super::super::lexer::lexer::Keyword::Else) => {
+                    let frame = cond_stack
+                        .last_mut()
+                        .ok_or_else(|| PreprocessError::new("#else without #ifdef/#ifndef"))?;
+                    frame.branch_active = !frame.branch_active;
+                }
+                Token::Identifier(name) if name == "endif" => {
+                    cond_stack
+                        .pop()
+                        .ok_or_else(|| PreprocessError::new("#endif without #ifdef/#ifndef"))?;
+                }
+                _ => return Err(PreprocessError::new("unknown preprocessor directive")),
+            }
+
+            Ok(())
+        }
+
+        fn expect_identifier(rest: &[SpannedToken], directive: &str) -> Result<String, PreprocessError> {
+            match rest.first().map(|t| &t.token) {
+                Some(Token::Identifier(name)) => Ok(name.clone()),
+                _ => Err(PreprocessError::new(format!(
+                    "expected a name after #{}",
+                    directive
+                ))),
+            }
+        }
+
+        fn handle_include(&mut self, rest: &[SpannedToken], out: &mut Vec<SpannedToken>) -> Result<(), PreprocessError> {
+            let raw_path = match rest.first().map(|t| &t.token) {
+                Some(Token::HeaderName(path)) => path.clone(),
+                Some(Token::StringLiteral(path)) => path.clone(),
+                _ => return Err(PreprocessError::new("expected a header name after #include")),
+            };
+
+            let resolved = self.resolve_include_path(&raw_path)?;
+            if self.include_stack.contains(&resolved) {
+                return Err(PreprocessError::new(format!(
+                    "cyclic #include of '{}'",
+                    resolved.display()
+                )));
+            }
+
+            let content = std::fs::read_to_string(&resolved).map_err(|e| {
+                PreprocessError::new(format!("cannot read '{}': {}", resolved.display(), e))
+            })?;
+
+            let mut tokens = Vec::new();
+            for result in Lexer::new(&content) {
+                tokens.push(result?);
+            }
+
+            self.include_stack.push(resolved);
+            let expanded = self.process(tokens);
+            self.include_stack.pop();
+            out.extend(expanded?);
+            Ok(())
+        }
+
+        fn resolve_include_path(&self, raw: &str) -> Result<PathBuf, PreprocessError> {
+            let direct = PathBuf::from(raw);
+            if direct.is_file() {
+                return Ok(direct);
+            }
+            for dir in &self.include_paths {
+                let candidate = dir.join(raw);
+                if candidate.is_file() {
+                    return Ok(candidate);
+                }
+            }
+            Err(PreprocessError::new(format!(
+                "cannot find header '{}'",
+                raw
+            )))
+        }
+
+        fn handle_define(&mut self, rest: &[SpannedToken]) -> Result<(), PreprocessError> {
+            let name = match rest.first() {
+                Some(tok) => match &tok.token {
+                    Token::Identifier(n) => n.clone(),
+                    _ => return Err(PreprocessError::new("expected a macro name after #define")),
+                },
+                None => return Err(PreprocessError::new("expected a macro name after #define")),
+            };
+            let name_tok = &rest[0];
+
+            // `FOO(x)` with no gap before `(` is a function-like macro;
+            // `FOO (x)` or `FOO` alone is an object-like macro whose value
+            // may simply start with a parenthesized expression.
+            let is_function_like = rest.len() > 1
+                && matches!(rest[1].token, Token::SpecialSymbol(SpecialSymbol::LeftParenthesis))
+                && name_tok.span.end.offset == rest[1].span.start.offset;
+
+            if is_function_like {
+                let (params, body_start) = Self::parse_macro_params(&rest[1..])?;
+                let body = rest[1 + body_start..].to_vec();
+                self.defines.insert(name, Macro::Function { params, body });
+            } else {
+                let body = rest[1..].to_vec();
+                self.defines.insert(name, Macro::Object(body));
+            }
+            Ok(())
+        }
+
+        // `tokens[0]` is the macro's opening `(`. Returns the parameter
+        // names and how many tokens (starting from `tokens[0]`) the list
+        // occupied, including the closing `)`.
+        fn parse_macro_params(tokens: &[SpannedToken]) -> Result<(Vec<String>, usize), PreprocessError> {
+            let mut params = Vec::new();
+            let mut i = 1;
+            if matches!(
+                tokens.get(i).map(|t| &t.token),
+                Some(Token::SpecialSymbol(SpecialSymbol::RightParenthesis))
+            ) {
+                return Ok((params, i + 1));
+            }
+            loop {
+                match tokens.get(i).map(|t| &t.token) {
+                    Some(Token::Identifier(name)) => params.push(name.clone()),
+                    _ => return Err(PreprocessError::new("expected a parameter name in macro definition")),
+                }
+                i += 1;
+                match tokens.get(i).map(|t| &t.token) {
+                    Some(Token::SpecialSymbol(SpecialSymbol::Comma)) => i += 1,
+                    Some(Token::SpecialSymbol(SpecialSymbol::RightParenthesis)) => {
+                        i += 1;
+                        break;
+                    }
+                    _ => return Err(PreprocessError::new("expected ',' or ')' in macro parameter list")),
+                }
+            }
+            Ok((params, i))
+        }
+
+        // Consumes `tokens[i]`, pushing either the token itself or (if it
+        // names a defined macro not already being expanded) its expansion
+        // onto `out`, and advances `i`
+        // past whatever was consumed. `active_macros` prevents a macro from
+        // expanding into itself.
+        fn expand_and_push(
+            &self,
+            tokens: &[SpannedToken],
+            i: &mut usize,
+            active_macros: &mut Vec<String>,
+            out: &mut Vec<SpannedToken>,
+        ) -> Result<(), PreprocessError> {
+            let tok = tokens[*i].clone();
+            if let Token::Identifier(name) = &tok.token {
+                if let Some(mac) = self.defines.get(name) {
+                    if !active_macros.contains(name) {
+                        match mac {
+                            Macro::Object(body) => {
+                                *i += 1;
+                                return self.expand_body(body, name, active_macros, out);
+                            }
+                            Macro::Function { params, body } => {
+                                if matches!(
+                                    tokens.get(*i + 1).map(|t| &t.token),
+                                    Some(Token::SpecialSymbol(SpecialSymbol::LeftParenthesis))
+                                ) {
+                                    let (args, after) = Self::collect_call_args(tokens, *i + 1)?;
+                                    if args.len() != params.len() {
+                                        return Err(PreprocessError::new(format!(
+                                            "macro '{}' expects {} argument(s), got {}",
+                                            name,
+                                            params.len(),
+                                            args.len()
+                                        )));
+                                    }
+                                    let substituted = Self::substitute(body, params, &args);
+                                    *i = after;
+                                    return self.expand_body(&substituted, name, active_macros, out);
+                                }
+                                // not followed by a call: leave it as a plain identifier
+                            }
+                        }
+                    }
+                }
+            }
+            out.push(tok);
+            *i += 1;
+            Ok(())
+        }
+
+        fn expand_body(
+            &self,
+            body: &[SpannedToken],
+            name: &str,
+            active_macros: &mut Vec<String>,
+            out: &mut Vec<SpannedToken>,
+        ) -> Result<(), PreprocessError> {
+            active_macros.push(name.to_string());
+            let mut j = 0;
+            while j < body.len() {
+                self.expand_and_push(body, &mut j, active_macros, out)?;
+            }
+            active_macros.pop();
+            Ok(())
+        }
+
+        fn substitute(body: &[SpannedToken], params: &[String], args: &[Vec<SpannedToken>]) -> Vec<SpannedToken> {
+            let mut out = Vec::new();
+            for tok in body {
+                if let Token::Identifier(name) = &tok.token {
+                    if let Some(idx) = params.iter().position(|p| p == name) {
+                        out.extend(args[idx].iter().cloned());
+                        continue;
+                    }
+                }
+                out.push(tok.clone());
+            }
+            out
+        }
+
+        // `tokens[open_idx]` is the call's opening `(`. Returns the
+        // (unexpanded) argument token lists and the index just past the
+        // matching `)`.
+        fn collect_call_args(
+            tokens: &[SpannedToken],
+            open_idx: usize,
+        ) -> Result<(Vec<Vec<SpannedToken>>, usize), PreprocessError> {
+            let mut depth = 0;
+            let mut args: Vec<Vec<SpannedToken>> = vec![Vec::new()];
+            let mut i = open_idx;
+
+            loop {
+                let tok = tokens
+                    .get(i)
+                    .ok_or_else(|| PreprocessError::new("unterminated macro invocation"))?;
+                match &tok.token {
+                    Token::SpecialSymbol(SpecialSymbol::LeftParenthesis) => {
+                        depth += 1;
+                        if depth > 1 {
+                            args.last_mut().unwrap().push(tok.clone());
+                        }
+                    }
+                    Token::SpecialSymbol(SpecialSymbol::RightParenthesis) => {
+                        depth -= 1;
+                        if depth == 0 {
+                            i += 1;
+                            break;
+                        }
+                        args.last_mut().unwrap().push(tok.clone());
+                    }
+                    Token::SpecialSymbol(SpecialSymbol::Comma) if depth == 1 => {
+                        args.push(Vec::new());
+                    }
+                    _ => {
+                        args.last_mut().unwrap().push(tok.clone());
+                    }
+                }
+                i += 1;
+            }
+
+            if args.len() == 1 && args[0].is_empty() {
+                args.clear();
+            }
+            Ok((args, i))
+        }
+    }
+
+    #[cfg(test)]
+    mod tests {
+        use super::*;
+
+        fn run(src: &str) -> Result<Vec<Token>, PreprocessError> {
+            let mut tokens = Vec::new();
+            for result in Lexer::new(src) {
+                tokens.push(result?);
+            }
+            let mut pp = Preprocessor::new(Vec::new());
+            Ok(pp.process(tokens)?.into_iter().map(|t| t.token).collect())
+        }
+
+        #[test]
+        fn object_like_macro_expands() {
+            let tokens = run("#define FOO 42\nFOO;").unwrap();
+            assert!(matches!(tokens[0], Token::IntLiteral { value: 42, .. }));
+        }
+
+        #[test]
+        fn function_like_macro_expands() {
+            let tokens = run("#define ADD(a, b) a + b\nADD(1, 2);").unwrap();
+            assert!(matches!(tokens[0], Token::IntLiteral { value: 1, .. }));
+            assert!(matches!(tokens[1], Token::SpecialSymbol(SpecialSymbol::Plus)));
+            assert!(matches!(tokens[2], Token::IntLiteral { value: 2, .. }));
+        }
+
+        #[test]
+        fn macro_arg_count_mismatch_is_an_error() {
+            let err = run("#define ADD(a, b) a + b\nADD(1);").unwrap_err();
+            assert!(err.message.contains("expects 2 argument"));
+        }
+
+        #[test]
+        fn ifdef_else_selects_active_branch() {
+            let tokens = run("#define FOO\n#ifdef FOO\n1;\n#else\n2;\n#endif").unwrap();
+            assert!(matches!(tokens[0], Token::IntLiteral { value: 1, .. }));
+
+            let tokens = run("#ifdef FOO\n1;\n#else\n2;\n#endif").unwrap();
+            assert!(matches!(tokens[0], Token::IntLiteral { value: 2, .. }));
+        }
+
+        #[test]
+        fn unterminated_conditional_is_an_error() {
+            let err = run("#ifdef FOO\n1;").unwrap_err();
+            assert!(err.message.contains("unterminated conditional"));
+        }
+
+        #[test]
+        fn cyclic_include_is_an_error() {
+            let dir = std::env::temp_dir().join(format!("ccinrust-pp-test-{}", std::process::id()));
+            std::fs::create_dir_all(&dir).unwrap();
+            std::fs::write(dir.join("a.h"), "#include \"b.h\"\n").unwrap();
+            std::fs::write(dir.join("b.h"), "#include \"a.h\"\n").unwrap();
+
+            let mut tokens = Vec::new();
+            for result in Lexer::new("#include \"a.h\"\n") {
+                tokens.push(result.unwrap());
+            }
+            let mut pp = Preprocessor::new(vec![dir.clone()]);
+            let err = pp.process(tokens).unwrap_err();
+            assert!(err.message.contains("cyclic #include"));
+
+            std::fs::remove_dir_all(&dir).unwrap();
+        }
+    }
+}
@@ -1,8 +1,9 @@
 mod lib;
 
-use lib::Lexer;
+use lib::{Lexer, LexerError, Parser, Preprocessor};
 use std::fs;
 use std::io::Read;
+use std::path::PathBuf;
 
 fn main() -> std::io::Result<()> {
     let mut file = fs::File::open("../hello.c")?;
@@ -10,24 +11,30 @@ fn main() -> std::io::Result<()> {
 
     file.read_to_string(&mut content)?;
 
-    let mut lex = Lexer::new(&content);
-
-    match &lex.lex() {
-        Ok(_) => {
-            println!("Successfully lex");
+    let mut tokens = Vec::new();
+    for result in Lexer::new(&content) {
+        match result {
+            Ok(token) => tokens.push(token),
+            Err(e) => {
+                println!("{}", e);
+                return Ok(());
+            }
         }
+    }
 
+    let mut preprocessor = Preprocessor::new(vec![PathBuf::from("..")]);
+    let tokens = match preprocessor.process(tokens) {
+        Ok(tokens) => tokens,
         Err(e) => {
-            if lex.get_status() {
-                println!("Finish");
-            } else {
-                println!("Error while lexing.\n{}", e);
-            }
+            println!("{}", e);
+            return Ok(());
         }
-    }
+    };
 
-    for token in lex.into_iter() {
-        println!("{}", token.to_string());
+    let mut parser = Parser::new(tokens.into_iter().map(Ok::<_, LexerError>));
+    match parser.parse() {
+        Ok(ast) => println!("{:#?}", ast),
+        Err(e) => println!("{}", e),
     }
     println!("Hello, world!");
     Ok(())